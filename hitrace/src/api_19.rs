@@ -1,7 +1,14 @@
+use std::borrow::Cow;
+use std::ffi::CStr;
 use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 use hitrace_sys::HiTrace_Output_Level;
 
+use crate::ScopedTrace;
+
 #[cfg(feature = "api-19")]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(i32)]
@@ -46,6 +53,116 @@ impl From<HiTraceOutputLevel> for HiTrace_Output_Level {
     }
 }
 
+/// Like [`crate::start_trace`], but lets the caller pick the HiTrace output
+/// level instead of always emitting at the (platform) default level, and
+/// attach `custom_args` that HiTrace records alongside the span.
+///
+/// Requires API level 19+, since it wraps `OH_HiTrace_StartTraceEx`.
+pub fn start_trace_ex<N: AsRef<CStr>>(
+    level: HiTraceOutputLevel,
+    name: &N,
+    custom_args: Option<&CStr>,
+) {
+    start_trace_ex_cstr(level, name.as_ref(), custom_args)
+}
+
+#[cfg(target_env = "ohos")]
+fn start_trace_ex_cstr(level: HiTraceOutputLevel, name: &CStr, custom_args: Option<&CStr>) {
+    let custom_args = custom_args.map_or(core::ptr::null(), |args| args.as_ptr());
+    // SAFETY: We have a valid CStr for `name`, and `custom_args` is either a valid
+    // CStr or null; both are copied by `OH_HiTrace_StartTraceEx`.
+    unsafe {
+        hitrace_sys::OH_HiTrace_StartTraceEx(level.into(), name.as_ptr(), custom_args);
+    }
+}
+
+#[cfg(not(target_env = "ohos"))]
+fn start_trace_ex_cstr(_: HiTraceOutputLevel, _: &CStr, _: Option<&CStr>) {}
+
+impl ScopedTrace {
+    /// Like [`ScopedTrace::start_trace`], but starts the span at a chosen
+    /// `level` via [`start_trace_ex`] instead of the platform default.
+    #[must_use]
+    pub fn start_trace_ex<N: AsRef<CStr>>(
+        level: HiTraceOutputLevel,
+        name: &N,
+        custom_args: Option<&CStr>,
+    ) -> Self {
+        start_trace_ex(level, name, custom_args);
+        // SAFETY: `start_trace_ex` above already opened the span; this just
+        // constructs the RAII guard that will close it on drop, without
+        // starting a second, nested span.
+        unsafe { ScopedTrace::_already_started() }
+    }
+
+    // A hidden function, analogous to `_start_trace_str_with_null`, which
+    // `hitrace-macro` uses to implement `#[trace_fn(level = "...")]`.
+    #[doc(hidden)]
+    pub unsafe fn _start_trace_ex_str_with_null(
+        level: HiTraceOutputLevel,
+        name_with_null: &str,
+    ) -> Self {
+        #[cfg(not(target_env = "ohos"))]
+        let _ = (level, name_with_null);
+        // SAFETY: The caller promises that `name_with_null` is a valid
+        // null-terminated C-style string.
+        #[cfg(target_env = "ohos")]
+        unsafe {
+            hitrace_sys::OH_HiTrace_StartTraceEx(
+                level.into(),
+                name_with_null.as_ptr(),
+                core::ptr::null(),
+            );
+        }
+        // SAFETY: the branch above already started the span.
+        unsafe { ScopedTrace::_already_started() }
+    }
+}
+
+/// Like [`crate::Instrumented`], but opens each poll's span at a chosen
+/// `level` via [`ScopedTrace::_start_trace_ex_str_with_null`] instead of the
+/// platform default.
+///
+/// Constructed by the `trace_fn` macro when applied to an `async fn` carrying
+/// `#[trace_fn(level = "...")]`; not meant to be built by hand.
+#[must_use = "futures do nothing unless polled or awaited"]
+pub struct InstrumentedEx<F> {
+    inner: F,
+    level: HiTraceOutputLevel,
+    name: Cow<'static, str>,
+}
+
+impl<F> InstrumentedEx<F> {
+    #[doc(hidden)]
+    pub fn new(inner: F, level: HiTraceOutputLevel, name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            inner,
+            level,
+            name: name.into(),
+        }
+    }
+}
+
+impl<F: Future> Future for InstrumentedEx<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `inner` is structurally pinned along with `self`: we never move
+        // it out, and `InstrumentedEx` is only ever accessed through this `Pin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+
+        // SAFETY: `this.name` is a null-terminated string produced by the
+        // `trace_fn` macro.
+        let _guard = unsafe {
+            ScopedTrace::_start_trace_ex_str_with_null(this.level, this.name.as_ref())
+        };
+        inner.poll(cx)
+        // `_guard` drops here, finishing the span before this poll call returns,
+        // whether the inner future is `Pending` or `Ready`.
+    }
+}
+
 #[cfg(feature = "tracing-level-conversion")]
 impl From<tracing_core::Level> for HiTraceOutputLevel {
     fn from(level: tracing_core::Level) -> Self{
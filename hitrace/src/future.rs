@@ -0,0 +1,54 @@
+//! Support for instrumenting `async fn`s.
+//!
+//! HiTrace's spans are stack-based: `finish_trace` always closes the most
+//! recently started span on the current thread. A single [`ScopedTrace`] guard
+//! held across an `.await` point would stay open while the thread runs
+//! unrelated work during suspension, corrupting that stack. [`Instrumented`]
+//! instead re-opens the span on every poll and closes it again before the poll
+//! returns, so the span never outlives a single poll call.
+
+use std::borrow::Cow;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::ScopedTrace;
+
+/// A [`Future`] that opens a HiTrace span for the duration of each poll of the
+/// wrapped future.
+///
+/// Constructed by the `trace_fn` macro when applied to an `async fn`; not
+/// meant to be built by hand.
+#[must_use = "futures do nothing unless polled or awaited"]
+pub struct Instrumented<F> {
+    inner: F,
+    name: Cow<'static, str>,
+}
+
+impl<F> Instrumented<F> {
+    #[doc(hidden)]
+    pub fn new(inner: F, name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            inner,
+            name: name.into(),
+        }
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `inner` is structurally pinned along with `self`: we never move
+        // it out, and `Instrumented` is only ever accessed through this `Pin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+
+        // SAFETY: `this.name` is a null-terminated string produced by the
+        // `trace_fn` macro.
+        let _guard = unsafe { ScopedTrace::_start_trace_str_with_null(this.name.as_ref()) };
+        inner.poll(cx)
+        // `_guard` drops here, finishing the span before this poll call returns,
+        // whether the inner future is `Pending` or `Ready`.
+    }
+}
@@ -0,0 +1,76 @@
+//! A [`tracing_subscriber::Layer`] that mirrors `tracing` spans into HiTrace.
+//!
+//! Gated behind the `tracing-subscriber` feature.
+
+use std::fmt::Write as _;
+
+use tracing_core::field::{Field, Visit};
+use tracing_core::span;
+use tracing_core::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Installs HiTrace spans for every `tracing` span that is entered.
+///
+/// On [`on_enter`](Layer::on_enter) this starts a HiTrace span named from the
+/// span's target, name, and (if cheap to render) its fields, and on
+/// [`on_exit`](Layer::on_exit) it finishes that span. This relies on
+/// `tracing`'s guarantee that enter/exit are strictly stack-ordered on a given
+/// thread, matching HiTrace's own stack-based span model; only span
+/// enter/exit are touched, never events, to keep the HiTrace stack balanced.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HiTraceLayer;
+
+impl<S> Layer<S> for HiTraceLayer
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut name = format!("{}::{}", span.metadata().target(), span.metadata().name());
+        let mut visitor = FieldVisitor(&mut name);
+        attrs.record(&mut visitor);
+        name.push('\0');
+
+        span.extensions_mut().insert(HiTraceName(name));
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(HiTraceName(name)) = extensions.get::<HiTraceName>() else {
+            return;
+        };
+
+        // SAFETY: `name` was built above and null-terminated right before storing it.
+        std::mem::forget(unsafe { crate::ScopedTrace::_start_trace_str_with_null(name) });
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        // Only finish a span if `on_enter` actually started one above, so
+        // start/finish stay balanced on HiTrace's per-thread span stack.
+        if span.extensions().get::<HiTraceName>().is_none() {
+            return;
+        }
+        crate::finish_trace();
+    }
+}
+
+struct HiTraceName(String);
+
+struct FieldVisitor<'a>(&'a mut String);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let _ = write!(self.0, "{{{}={:?}}}", field.name(), value);
+    }
+}
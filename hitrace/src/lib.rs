@@ -37,6 +37,19 @@
 use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
 
+mod future;
+pub use future::Instrumented;
+
+#[cfg(feature = "tracing-subscriber")]
+mod layer;
+#[cfg(feature = "tracing-subscriber")]
+pub use layer::HiTraceLayer;
+
+#[cfg(feature = "api-19")]
+mod api_19;
+#[cfg(feature = "api-19")]
+pub use api_19::{start_trace_ex, HiTraceOutputLevel, InstrumentedEx};
+
 pub fn start_trace<T: AsRef<CStr>>(name: &T) {
     start_trace_cstr(name.as_ref())
 }
@@ -70,6 +83,33 @@ pub fn finish_trace() {
     finish_trace_()
 }
 
+/// Emits a counter trace sample: a named, numeric value (e.g. a queue depth,
+/// byte count, or FPS) that shows up as a value-over-time track rather than a
+/// span.
+pub fn trace_count<T: AsRef<CStr>>(name: &T, count: i64) {
+    trace_count_cstr(name.as_ref(), count)
+}
+
+#[cfg(target_env = "ohos")]
+fn trace_count_cstr(name: &CStr, count: i64) {
+    // SAFETY: We have a valid CStr, which is copied by `OH_HiTrace_CountTrace`.
+    unsafe {
+        hitrace_sys::OH_HiTrace_CountTrace(name.as_ptr(), count);
+    }
+}
+
+#[cfg(not(target_env = "ohos"))]
+fn trace_count_cstr(_: &CStr, _: i64) {}
+
+/// Like `trace_count()` but accepts a `&str`.
+///
+/// # Panic
+///
+/// Panics if the provided name can't be converted into a CString.
+pub fn trace_count_str(name: &str, count: i64) {
+    trace_count(&CString::new(name).expect("Contained null-byte"), count)
+}
+
 pub struct ScopedTrace {
     // Remove Send / Sync, since the trace needs to be finished on the same thread.
     phantom_data: PhantomData<*mut u8>,
@@ -115,6 +155,15 @@ impl ScopedTrace {
             phantom_data: PhantomData,
         }
     }
+
+    // Builds a guard for a span that has already been started through some
+    // other path (e.g. `start_trace_ex`), without starting another one.
+    #[doc(hidden)]
+    pub(crate) unsafe fn _already_started() -> Self {
+        Self {
+            phantom_data: PhantomData,
+        }
+    }
 }
 
 impl Drop for ScopedTrace {
@@ -123,11 +172,87 @@ impl Drop for ScopedTrace {
     }
 }
 
+/// Starts an asynchronous trace span identified by `name` and `task_id`.
+///
+/// Unlike [`start_trace`]/[`finish_trace`], asynchronous traces aren't
+/// stack-based: the span identified by a given `(name, task_id)` pair may be
+/// started on one thread and finished on another, and several async traces
+/// may overlap. This is the right primitive for instrumenting futures and
+/// cross-thread callbacks, where [`ScopedTrace`] would be unsound.
+pub fn start_async_trace<T: AsRef<CStr>>(name: &T, task_id: i32) {
+    start_async_trace_cstr(name.as_ref(), task_id)
+}
+
+#[cfg(target_env = "ohos")]
+fn start_async_trace_cstr(name: &CStr, task_id: i32) {
+    // SAFETY: We have a valid CStr, which is copied by `OH_HiTrace_StartAsyncTrace`.
+    unsafe {
+        hitrace_sys::OH_HiTrace_StartAsyncTrace(name.as_ptr(), task_id);
+    }
+}
+
+#[cfg(not(target_env = "ohos"))]
+fn start_async_trace_cstr(_: &CStr, _: i32) {}
+
+/// Finishes the asynchronous trace span identified by `name` and `task_id`.
+pub fn finish_async_trace<T: AsRef<CStr>>(name: &T, task_id: i32) {
+    finish_async_trace_cstr(name.as_ref(), task_id)
+}
+
+#[cfg(target_env = "ohos")]
+fn finish_async_trace_cstr(name: &CStr, task_id: i32) {
+    // SAFETY: We have a valid CStr, which is copied by `OH_HiTrace_FinishAsyncTrace`.
+    unsafe {
+        hitrace_sys::OH_HiTrace_FinishAsyncTrace(name.as_ptr(), task_id);
+    }
+}
+
+#[cfg(not(target_env = "ohos"))]
+fn finish_async_trace_cstr(_: &CStr, _: i32) {}
+
+/// An RAII guard around a HiTrace asynchronous trace span, started by
+/// [`AsyncTrace::start_async_trace`] and finished when dropped.
+///
+/// Unlike [`ScopedTrace`], an `AsyncTrace` may be created on one thread and
+/// dropped on another, or kept alive across `.await` points, since
+/// asynchronous traces aren't tied to HiTrace's per-thread span stack.
+pub struct AsyncTrace {
+    name: CString,
+    task_id: i32,
+}
+
+impl AsyncTrace {
+    /// Starts a new `AsyncTrace`, which ends when the returned object is dropped.
+    #[must_use]
+    pub fn start_async_trace<T: AsRef<CStr>>(name: &T, task_id: i32) -> Self {
+        let name = name.as_ref().to_owned();
+        start_async_trace(&name, task_id);
+        Self { name, task_id }
+    }
+
+    /// Like `start_async_trace()` but accepts a `&str`.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the provided name can't be converted into a CString.
+    #[must_use]
+    pub fn start_async_trace_str(name: &str, task_id: i32) -> Self {
+        Self::start_async_trace(&CString::new(name).expect("Contained null-byte"), task_id)
+    }
+}
+
+impl Drop for AsyncTrace {
+    fn drop(&mut self) {
+        finish_async_trace(&self.name, self.task_id)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use static_assertions::assert_not_impl_any;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
 
-    use crate::ScopedTrace;
+    use crate::{AsyncTrace, ScopedTrace};
 
     assert_not_impl_any!(ScopedTrace: Send, Sync);
+    assert_impl_all!(AsyncTrace: Send, Sync);
 }
@@ -20,17 +20,234 @@
 //! }
 //! ```
 //!
+//! `trace_fn` accepts a few optional arguments, following `tracing`'s
+//! `#[instrument]`: `#[trace_fn(name = "...")]` overrides the span name,
+//! `#[trace_fn(fields(a, b))]` (or bare `#[trace_fn(a, b)]`) appends the given
+//! parameters' `Debug` output to it, and `#[trace_fn(skip(c))]` captures every
+//! parameter except `c`. `#[trace_fn(level = "...")]` requires the `api-19`
+//! feature.
+//!
 
 use proc_macro::TokenStream;
 
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{quote, ToTokens};
-use syn::{parse_macro_input, ItemMod};
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, parse_macro_input, FnArg, Ident, ItemMod, LitStr, Pat, Signature, Token};
+
+/// Which function parameters (if any) get formatted into the span name.
+#[derive(Default)]
+enum Capture {
+    /// No parameters are captured (the default, and the only behavior prior
+    /// to `fields`/`skip`/bare-ident support).
+    #[default]
+    None,
+    /// Exactly the named parameters, from `fields(a, b)` or bare `a, b`.
+    Only(Vec<Ident>),
+    /// Every parameter except the named ones, from `skip(a, b)`.
+    AllExcept(Vec<Ident>),
+}
+
+/// Parsed `#[trace_fn(...)]` attribute arguments.
+#[derive(Default)]
+struct TraceFnArgs {
+    /// `level = "debug" | "info" | "critical" | "commercial"`, requires `api-19`.
+    level: Option<LitStr>,
+    /// `name = "..."`, overriding the default `module_path!()::fn_name` span name.
+    name: Option<LitStr>,
+    capture: Capture,
+}
+
+impl Parse for TraceFnArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = TraceFnArgs::default();
+        let mut captured = Vec::new();
+        let mut skipped = None;
+
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            match ident.to_string().as_str() {
+                "level" => {
+                    input.parse::<Token![=]>()?;
+                    args.level = Some(input.parse()?);
+                }
+                "name" => {
+                    input.parse::<Token![=]>()?;
+                    args.name = Some(input.parse()?);
+                }
+                "fields" => {
+                    let content;
+                    parenthesized!(content in input);
+                    captured.extend(content.parse_terminated(Ident::parse, Token![,])?);
+                }
+                "skip" => {
+                    let content;
+                    parenthesized!(content in input);
+                    skipped = Some(
+                        content
+                            .parse_terminated(Ident::parse, Token![,])?
+                            .into_iter()
+                            .collect::<Vec<_>>(),
+                    );
+                }
+                // Bare argument capture, e.g. `#[trace_fn(req_id)]`.
+                _ => captured.push(ident),
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        args.capture = match (captured.is_empty(), skipped) {
+            (false, _) => Capture::Only(captured),
+            (true, Some(skipped)) => Capture::AllExcept(skipped),
+            (true, None) => Capture::None,
+        };
+
+        Ok(args)
+    }
+}
+
+impl TraceFnArgs {
+    /// The parameters to format into the span name, resolved against `sig`.
+    fn captured_params(&self, sig: &Signature) -> Vec<Ident> {
+        match &self.capture {
+            Capture::None => Vec::new(),
+            Capture::Only(idents) => idents.clone(),
+            Capture::AllExcept(skip) => fn_param_idents(sig)
+                .into_iter()
+                .filter(|param| !skip.contains(param))
+                .collect(),
+        }
+    }
+
+    /// The `hitrace::ScopedTrace::_start_trace*_str_with_null` call to open the
+    /// span, given the null-terminated name expression.
+    fn start_expr(&self, name: &TokenStream2) -> TokenStream2 {
+        match &self.level {
+            Some(level) => {
+                let level_ident = level_ident(level);
+                quote!(unsafe {
+                    hitrace::ScopedTrace::_start_trace_ex_str_with_null(
+                        hitrace::HiTraceOutputLevel::#level_ident,
+                        #name,
+                    )
+                })
+            }
+            None => quote!(unsafe { hitrace::ScopedTrace::_start_trace_str_with_null(#name) }),
+        }
+    }
+
+    /// The `hitrace::Instrumented[Ex]::new(..)` call that wraps an
+    /// instrumented `async fn`'s body future, given that future's expression
+    /// and the null-terminated name expression.
+    fn instrument_future_expr(&self, future: &TokenStream2, name: &TokenStream2) -> TokenStream2 {
+        match &self.level {
+            Some(level) => {
+                let level_ident = level_ident(level);
+                quote!(hitrace::InstrumentedEx::new(
+                    #future,
+                    hitrace::HiTraceOutputLevel::#level_ident,
+                    #name,
+                ))
+            }
+            None => quote!(hitrace::Instrumented::new(#future, #name)),
+        }
+    }
+}
+
+fn level_ident(level: &LitStr) -> Ident {
+    let ident = match level.value().as_str() {
+        "debug" => "Debug",
+        "info" => "Info",
+        "critical" => "Critical",
+        "commercial" => "Commercial",
+        other => panic!("unknown HiTrace output level `{other}`"),
+    };
+    Ident::new(ident, level.span())
+}
+
+fn fn_param_idents(sig: &Signature) -> Vec<Ident> {
+    sig.inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => Some(pat_ident.ident.clone()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// The statements that build the HITRACE_THIS_FN_NAME (or, with captured
+/// fields, `__hitrace_name`) binding, plus expressions referring to it: a
+/// `&str` for the synchronous `_start_trace*_str_with_null` calls, and an
+/// owned `impl Into<Cow<'static, str>>` for `Instrumented[Ex]::new`, which
+/// needs to hold onto the name across poll calls.
+struct SpanName {
+    stmts: Vec<syn::Stmt>,
+    ref_expr: TokenStream2,
+    owned_expr: TokenStream2,
+}
+
+fn build_span_name(func: &syn::ItemFn, args: &TraceFnArgs) -> SpanName {
+    let fn_name = func.sig.ident.to_string();
+    let base = match &args.name {
+        Some(name) => quote!(#name),
+        None => quote!(concat!(module_path!(), "::", #fn_name)),
+    };
+
+    let captured = args.captured_params(&func.sig);
+    if captured.is_empty() {
+        let stmt = syn::parse2(quote!(
+            const HITRACE_THIS_FN_NAME: &str = concat!(#base, "\0");
+        ))
+        .unwrap();
+        return SpanName {
+            stmts: vec![stmt],
+            ref_expr: quote!(HITRACE_THIS_FN_NAME),
+            owned_expr: quote!(HITRACE_THIS_FN_NAME),
+        };
+    }
+
+    let base_stmt = syn::parse2(quote!(
+        const HITRACE_THIS_FN_BASE_NAME: &str = concat!(#base);
+    ))
+    .unwrap();
+    let field_writes = captured.iter().map(|ident| {
+        let ident_str = ident.to_string();
+        quote!(
+            let _ = ::std::fmt::Write::write_fmt(
+                &mut __hitrace_name,
+                format_args!("{{{}={:?}}}", #ident_str, #ident),
+            );
+        )
+    });
+    let build_stmt = syn::parse2(quote!(
+        let __hitrace_name = {
+            let mut __hitrace_name = ::std::string::String::from(HITRACE_THIS_FN_BASE_NAME);
+            #(#field_writes)*
+            __hitrace_name.push('\0');
+            __hitrace_name
+        };
+    ))
+    .unwrap();
+
+    SpanName {
+        stmts: vec![base_stmt, build_stmt],
+        ref_expr: quote!(__hitrace_name.as_str()),
+        owned_expr: quote!(__hitrace_name),
+    }
+}
 
 #[proc_macro_attribute]
-pub fn trace_fn(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn trace_fn(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as TraceFnArgs);
     let input = TokenStream2::from(input);
-    trace_fn_(input).into()
+    trace_fn_(args, input).into()
 }
 
 #[proc_macro_attribute]
@@ -39,29 +256,85 @@ pub fn trace_all_fns(_args: TokenStream, input: TokenStream) -> TokenStream {
     trace_all_fns_in_mod(input_mod).into()
 }
 
-fn trace_fn_(input: TokenStream2) -> TokenStream2 {
+fn trace_fn_(args: TraceFnArgs, input: TokenStream2) -> TokenStream2 {
     let mut item: syn::Item = syn::parse2(input).unwrap();
     let func = match &mut item {
         syn::Item::Fn(func) => func,
         _ => panic!("Expected a function"),
     };
-    let fn_name = func.sig.ident.to_string();
-    let fn_name_statement = quote!(
-        const HITRACE_THIS_FN_NAME: &str = concat!(module_path!(), "::", #fn_name, "\0");
-    );
-    let call_hitrace = quote!(
-        let guard = unsafe { hitrace::ScopedTrace::_start_trace_str_with_null(HITRACE_THIS_FN_NAME) };
-    );
-    let parsed_name_stmt: syn::Stmt = syn::parse2(fn_name_statement).unwrap();
-    let call_hitrace_stmt: syn::Stmt = syn::parse2(call_hitrace).unwrap();
-
-    func.block.stmts.insert(0, parsed_name_stmt);
-    func.block.stmts.insert(1, call_hitrace_stmt);
+    instrument_fn(func, &args);
 
     item.into_token_stream()
 }
 
-fn trace_all_fns_in_mod(input_mod: ItemMod) -> TokenStream2 {
-    let elements = input_mod.content;
-    todo!()
+/// Applies the `trace_fn` transformation to every top-level `fn` in `input_mod`,
+/// leaving non-function items, nested modules, and already-instrumented functions
+/// untouched.
+fn trace_all_fns_in_mod(mut input_mod: ItemMod) -> TokenStream2 {
+    if let Some((_, items)) = &mut input_mod.content {
+        for item in items {
+            if let syn::Item::Fn(func) = item {
+                if is_already_instrumented(func) {
+                    continue;
+                }
+                instrument_fn(func, &TraceFnArgs::default());
+            }
+        }
+    }
+
+    input_mod.into_token_stream()
+}
+
+fn is_already_instrumented(func: &syn::ItemFn) -> bool {
+    func.attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("trace_fn"))
+}
+
+fn instrument_fn(func: &mut syn::ItemFn, args: &TraceFnArgs) {
+    if func.sig.asyncness.is_some() {
+        instrument_async_fn(func, args);
+    } else {
+        instrument_sync_fn(func, args);
+    }
+}
+
+fn instrument_sync_fn(func: &mut syn::ItemFn, args: &TraceFnArgs) {
+    let span_name = build_span_name(func, args);
+    let start_expr = args.start_expr(&span_name.ref_expr);
+    let call_hitrace_stmt: syn::Stmt = syn::parse2(quote!(
+        let guard = #start_expr;
+    ))
+    .unwrap();
+
+    let insert_at = span_name.stmts.len();
+    for (offset, stmt) in span_name.stmts.into_iter().enumerate() {
+        func.block.stmts.insert(offset, stmt);
+    }
+    func.block.stmts.insert(insert_at, call_hitrace_stmt);
+}
+
+/// Instruments an `async fn`.
+///
+/// A single `ScopedTrace` guard can't span the function's body, since HiTrace
+/// is stack-based and the thread is free to do unrelated work between polls.
+/// Instead of touching the signature (which would drop any input lifetimes
+/// the returned future needs to borrow, e.g. for `&self`/`&T` parameters),
+/// the function stays `async fn` and its body is wrapped in an inner
+/// `async move` block driven by `hitrace::Instrumented` (or
+/// `hitrace::InstrumentedEx` when a `level` is given): that wrapper
+/// re-opens the span on every poll and closes it again before the poll
+/// returns, so the span never crosses an `.await` suspension point.
+fn instrument_async_fn(func: &mut syn::ItemFn, args: &TraceFnArgs) {
+    let span_name = build_span_name(func, args);
+
+    let name_stmts = &span_name.stmts;
+    let body = &func.block;
+    let future_expr = quote!(async move #body);
+    let instrument_expr = args.instrument_future_expr(&future_expr, &span_name.owned_expr);
+    let new_block = quote!({
+        #(#name_stmts)*
+        #instrument_expr.await
+    });
+    *func.block = syn::parse2(new_block).unwrap();
 }
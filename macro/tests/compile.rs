@@ -28,6 +28,79 @@ mod hitrace {
                 .expect("Test should not have ended yet");
         }
     }
+
+    use std::borrow::Cow;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    pub struct Instrumented<F> {
+        inner: F,
+        name: Cow<'static, str>,
+    }
+
+    impl<F> Instrumented<F> {
+        pub fn new(inner: F, name: impl Into<Cow<'static, str>>) -> Self {
+            Self {
+                inner,
+                name: name.into(),
+            }
+        }
+    }
+
+    impl<F: Future> Future for Instrumented<F> {
+        type Output = F::Output;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let last_byte = self.name.as_bytes().last().expect("empty name");
+            assert_eq!(*last_byte, 0, "Last byte must be null");
+            // SAFETY: `inner` is structurally pinned along with `self`.
+            let inner = unsafe { self.map_unchecked_mut(|this| &mut this.inner) };
+            inner.poll(cx)
+        }
+    }
+}
+
+/// Drives `fut` to completion on the current thread, without pulling in an
+/// async runtime: every future instrumented by `trace_fn` in this test
+/// resolves on its first poll, since none of them actually suspend.
+fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    unsafe fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    unsafe fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    let waker = unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) };
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = Box::pin(fut);
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[trace_fn]
+async fn measure_len(input: &str) -> usize {
+    input.len()
+}
+
+struct Thing;
+
+impl Thing {
+    #[trace_fn]
+    async fn measure(&self) -> usize {
+        42
+    }
+}
+
+#[test]
+fn check_async_instrumentation() {
+    assert_eq!(block_on(measure_len("hello")), 5);
+    assert_eq!(block_on(Thing.measure()), 42);
 }
 
 #[trace_fn]